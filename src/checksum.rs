@@ -0,0 +1,56 @@
+use std::{fs, path::Path};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use sha2::{Digest as _, Sha256};
+
+use crate::error::DownloadError;
+
+/// The digest a completed download is checked against. `ContentMd5` comes
+/// from the server's `content-md5` header; `Sha256` is supplied by the
+/// caller when content authenticity matters more than trusting the server.
+#[derive(Debug, Clone)]
+pub enum Checksum {
+    ContentMd5(String),
+    Sha256(String),
+}
+
+/// Re-reads the assembled file and checks it against `checksum`. When no
+/// checksum is available at all (no header, no caller-supplied digest),
+/// this falls back to verifying the on-disk size matches `len`.
+pub fn verify_file(path: &Path, len: u64, checksum: Option<&Checksum>) -> Result<(), DownloadError> {
+    let bytes = fs::read(path)?;
+
+    if bytes.len() as u64 != len {
+        return Err(DownloadError::SizeMismatch {
+            expected: len,
+            got: bytes.len() as u64,
+        });
+    }
+
+    match checksum {
+        Some(Checksum::ContentMd5(expected)) => {
+            let digest = md5::compute(&bytes);
+            let actual = BASE64.encode(digest.0);
+
+            if &actual != expected {
+                return Err(DownloadError::ChecksumMismatch {
+                    expected: expected.clone(),
+                    got: actual,
+                });
+            }
+        }
+        Some(Checksum::Sha256(expected)) => {
+            let actual = hex::encode(Sha256::digest(&bytes));
+
+            if !actual.eq_ignore_ascii_case(expected) {
+                return Err(DownloadError::ChecksumMismatch {
+                    expected: expected.clone(),
+                    got: actual,
+                });
+            }
+        }
+        None => {}
+    }
+
+    Ok(())
+}
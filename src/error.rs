@@ -0,0 +1,43 @@
+use std::fmt;
+
+/// Everything that can go wrong while fetching and persisting a block or
+/// verifying the finished file; one variant per failure so callers can
+/// match on the retryable cases.
+#[derive(Debug)]
+pub enum DownloadError {
+    Request(hyper::Error),
+    UnexpectedStatus(hyper::StatusCode),
+    ShortRead { expected: u64, got: u64 },
+    Io(std::io::Error),
+    RetriesExhausted(Box<DownloadError>),
+    SizeMismatch { expected: u64, got: u64 },
+    ChecksumMismatch { expected: String, got: String },
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request(e) => write!(f, "request failed: {e}"),
+            Self::UnexpectedStatus(s) => write!(f, "unexpected status: {s}"),
+            Self::ShortRead { expected, got } => {
+                write!(f, "short read: expected {expected} bytes, got {got}")
+            }
+            Self::Io(e) => write!(f, "io error: {e}"),
+            Self::RetriesExhausted(cause) => write!(f, "retries exhausted: {cause}"),
+            Self::SizeMismatch { expected, got } => {
+                write!(f, "size mismatch: expected {expected} bytes, got {got}")
+            }
+            Self::ChecksumMismatch { expected, got } => {
+                write!(f, "checksum mismatch: expected {expected}, got {got}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DownloadError {}
+
+impl From<std::io::Error> for DownloadError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    // Video
+    Mp4,
+    Flv,
+    M3u8,
+    Ts,
+    Mov,
+    Avi,
+    Wmv,
+
+    // Image
+    Jpeg,
+    Png,
+    Tiff,
+    Gif,
+    Svg,
+    Webp,
+
+    // Audio
+    Ogg,
+    Wav,
+    Mp3,
+
+    // Other
+    Unknow,
+}
+
+impl FileType {
+    /// Picks a `FileType` for the given `content-type` header value, falling
+    /// back to sniffing `uri`'s path extension when the header is missing
+    /// or too generic (`application/octet-stream`) to trust.
+    pub fn detect(content_type: Option<&str>, uri: &str) -> Self {
+        match content_type {
+            Some(mime) if !mime.is_empty() && mime != "application/octet-stream" => {
+                Self::from_mime(mime).unwrap_or_else(|| Self::from_uri(uri))
+            }
+            _ => Self::from_uri(uri),
+        }
+    }
+
+    fn from_mime(mime: &str) -> Option<Self> {
+        Some(match mime {
+            "video/x-flv" => Self::Flv,
+            "video/mp4" => Self::Mp4,
+            "application/x-mpegURL" => Self::M3u8,
+            "video/MP2T" => Self::Ts,
+            "video/quicktime" => Self::Mov,
+            "video/x-msvideo" => Self::Avi,
+            "video/x-ms-wmv" => Self::Wmv,
+            "audio/x-wav" => Self::Wav,
+            "audio/x-mp3" | "audio/mpeg" => Self::Mp3,
+            "application/ogg" => Self::Ogg,
+            "image/jpeg" => Self::Jpeg,
+            "image/png" => Self::Png,
+            "image/tiff" => Self::Tiff,
+            "image/gif" => Self::Gif,
+            "image/svg+xml" => Self::Svg,
+            "image/webp" => Self::Webp,
+            _ => return None,
+        })
+    }
+
+    fn from_uri(uri: &str) -> Self {
+        let path = uri.split(&['?', '#'][..]).next().unwrap_or_default();
+        let ext = path
+            .rsplit('/')
+            .next()
+            .and_then(|name| name.rsplit_once('.'))
+            .map(|(_, ext)| ext)
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        match ext.as_str() {
+            "flv" => Self::Flv,
+            "mp4" => Self::Mp4,
+            "m3u8" => Self::M3u8,
+            "ts" => Self::Ts,
+            "mov" => Self::Mov,
+            "avi" => Self::Avi,
+            "wmv" => Self::Wmv,
+            "wav" => Self::Wav,
+            "mp3" => Self::Mp3,
+            "ogg" => Self::Ogg,
+            "jpg" | "jpeg" => Self::Jpeg,
+            "png" => Self::Png,
+            "tiff" | "tif" => Self::Tiff,
+            "gif" => Self::Gif,
+            "svg" => Self::Svg,
+            "webp" => Self::Webp,
+            _ => Self::Unknow,
+        }
+    }
+}
+
+impl fmt::Display for FileType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let suffix = match self {
+            Self::Flv => "flv",
+            Self::Mp4 => "mp4",
+            Self::M3u8 => "m3u8",
+            Self::Ts => "ts",
+            Self::Mov => "mov",
+            Self::Avi => "avi",
+            Self::Wmv => "wmv",
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+            Self::Tiff => "tiff",
+            Self::Gif => "gif",
+            Self::Svg => "svg",
+            Self::Webp => "webp",
+            Self::Ogg => "ogg",
+            Self::Wav => "wav",
+            Self::Mp3 => "mp3",
+            // No extension is recognizable, but a bare trailing dot in the
+            // filename is worse, so fall back to a generic binary suffix.
+            Self::Unknow => "bin",
+        };
+
+        write!(f, "{suffix}")
+    }
+}
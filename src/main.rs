@@ -1,14 +1,32 @@
-use log;
-use simple_logger;
-use std::{fs, os::unix::fs::FileExt, path::Path, sync::Arc};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use hyper::{client::HttpConnector, Body, Client, HeaderMap, Method, Request, Response};
-use hyper_tls;
-use rayon::prelude::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use hyper::{body::HttpBody, client::HttpConnector, Body, Client, HeaderMap, Method, Request};
 
 use futures::future;
 
-const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64; rv:12.0) Gecko/20100101 Firefox/12.0";
+mod checksum;
+mod error;
+mod file_type;
+mod manifest;
+mod partition;
+mod processor;
+mod segment;
+
+use checksum::Checksum;
+use error::DownloadError;
+use file_type::FileType;
+use manifest::{Manifest, SharedManifest};
+use partition::PartitionPolicy;
+use segment::{SegmentIndex, SegmentPolicy, SegmentWriter};
+
+/// Number of times a failed range request is re-issued before the block
+/// is given up on.
+const MAX_RETRIES: u32 = 5;
+const RETRY_BASE_BACKOFF: Duration = Duration::from_millis(200);
 
 #[tokio::main]
 async fn main() {
@@ -26,19 +44,65 @@ async fn main() {
     let response = client.request(request).await.unwrap();
     let headers = response.headers();
 
-    let info = FileInfo::new(headers, uri, "w", 8);
+    let info = FileInfo::new(
+        headers,
+        uri,
+        "w",
+        PartitionPolicy::Threads(8),
+        None,
+        SegmentPolicy::None,
+    );
+    let manifest: SharedManifest = Arc::new(Mutex::new(Manifest::from_blocks(
+        &info.uri,
+        info.len,
+        &info.blocks,
+    )));
 
     let mut join = Vec::with_capacity(info.blocks.len());
 
-    println!("{:#?}", info);
+    // Bind references outside the loop so each `async move` block captures
+    // a `&Client`/`&FileInfo` by value (cheap, Copy) instead of moving the
+    // shared client/info themselves into the first future.
+    let client = &client;
+    let info_ref = &info;
+
     for f in info.blocks.iter() {
-        join.push(async {
-            f.download(&client, &info).await.unwrap();
+        let manifest = Arc::clone(&manifest);
+        join.push(async move {
+            f.download(client, info_ref, &manifest).await.unwrap();
             log::info!("DONE: {}", f.id);
         });
     }
 
     future::join_all(join).await; // run
+
+    let all_done = manifest.lock().unwrap().blocks.iter().all(|b| b.is_done);
+    if all_done {
+        match info.segments.single_file_path(&info.working_path()) {
+            Some(working_path) => {
+                checksum::verify_file(Path::new(&working_path), info.len, info.checksum.as_ref())
+                    .unwrap();
+            }
+            None => {
+                log::info!("segmented output: skipping whole-file checksum, see segments.json");
+            }
+        }
+
+        info.segments
+            .finalize(&info.working_path(), &info.save_as)
+            .unwrap();
+        if info.segments.single_file_path(&info.save_as).is_none() {
+            info.segments.save_index(&info.save_as).unwrap();
+        }
+        Manifest::remove(&info.save_as).unwrap();
+
+        if let Some(processor) = processor::processor_for(info.file_type) {
+            match processor.process(Path::new(&info.save_as), info.file_type).await {
+                Ok(derivatives) => log::info!("generated derivatives: {derivatives:?}"),
+                Err(err) => log::warn!("post-download processing failed: {err}"),
+            }
+        }
+    }
 }
 
 pub fn init() {
@@ -55,49 +119,29 @@ pub fn init() {
 pub struct FileInfo {
     uri: String,
     len: u64,
-    suffix: String,
     save_as: String,
+    // Kept on the struct (and shown via the `Debug` derive) even though
+    // nothing reads them back after construction: they record *why*
+    // `blocks` was partitioned/resumed the way it was.
+    #[allow(dead_code)]
     flag_range: bool,
-    thread: u8,
+    #[allow(dead_code)]
+    partition: PartitionPolicy,
     blocks: Vec<Block>,
-    blocks_count: u64,
-    block_offset: u64,
-    block_offset_head: u64,
-    has_write: u64,
-}
-
-#[derive(Debug, Clone, Copy)]
-pub enum FileType {
-    // Video
-    Mp4,
-
-    // Image
-    Jpeg,
-    Png,
-
-    // Audio
-    Ogg,
-
-    // Other
-    Unknow,
-}
-
-impl std::fmt::Display for FileType {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let suffix_ = match self {
-            Self::Png => "png",
-            Self::Jpeg => "jpg",
-            Self::Mp4 => "mp4",
-            Self::Ogg => "ogg",
-            _ => "",
-        };
-
-        write!(f, "{suffix_}")
-    }
+    checksum: Option<Checksum>,
+    file_type: FileType,
+    segments: SegmentIndex,
 }
 
 impl FileInfo {
-    fn new(headers: &HeaderMap, uri: &str, save_as: &str, thread: u8) -> Self {
+    fn new(
+        headers: &HeaderMap,
+        uri: &str,
+        save_as: &str,
+        partition: PartitionPolicy,
+        expected_sha256: Option<String>,
+        segment_policy: SegmentPolicy,
+    ) -> Self {
         let len = headers
             .get("content-length")
             .unwrap()
@@ -105,76 +149,72 @@ impl FileInfo {
             .unwrap()
             .parse::<u64>()
             .unwrap();
-        let block_offset = len / thread as u64;
-        let block_offset_head = len % block_offset;
-        let blocks_count = (len / block_offset) + 1;
-        let file_type: FileType = if let Some(t) = headers.get("content-type") {
-            match t.to_str().unwrap() {
-                //"video/x-flv" => ".flv",
-                "video/mp4" => FileType::Png,
-                //"application/x-mpegURL" => ".m3u8",
-                //"video/MP2T" => ".ts",
-                //"video/3gpp" => ".3gpp",
-                //"video/quicktime" => ".mov",
-                //"video/x-msvideo" => ".avi",
-                //"video/x-ms-wmv" => ".wmv",
-                //"audio/x-wav" => ".wav",
-                //"audio/x-mp3" => ".mp3",
-                //"audio/mp4" => ".mp4",
-                "application/ogg" => FileType::Ogg,
-                "image/jpeg" => FileType::Jpeg,
-                "image/png" => FileType::Png,
-                //"image/tiff" => ".tiff",
-                //"image/gif" => ".gif",
-                //"image/svg+xml" => ".svg",
-                _ => FileType::Unknow,
-            }
-        } else {
-            FileType::Unknow
+        let file_type = FileType::detect(
+            headers.get("content-type").and_then(|v| v.to_str().ok()),
+            uri,
+        );
+
+        let mut blocks: Vec<Block> = partition::partition(len, partition)
+            .into_iter()
+            .enumerate()
+            .map(|(id, (start, end))| Block::new(id as u64, start, end))
+            .collect();
+
+        let save_as = format!("{save_as}.{file_type}");
+        let flag_range = match headers.get("accept-ranges") {
+            None => false,
+            Some(v) => v.to_str().unwrap().eq("bytes"),
+        };
+        let content_md5 = headers
+            .get("content-md5")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+
+        // A caller-supplied SHA-256 is authoritative; otherwise fall back to
+        // whatever content-md5 the server advertised, if any.
+        let checksum = match (expected_sha256, content_md5) {
+            (Some(sha256), _) => Some(Checksum::Sha256(sha256)),
+            (None, Some(md5)) => Some(Checksum::ContentMd5(md5)),
+            (None, None) => None,
         };
 
-        let mut blocks = Vec::with_capacity(blocks_count as usize);
-        let mut id = 0;
-        let mut start = 0;
-        let mut end = block_offset_head;
-
-        blocks.push(Block {
-            id,
-            start,
-            end,
-            is_done: false,
-        });
-
-        for f in 0..blocks_count - 1 {
-            id += 1;
-            start = end + 1;
-            end += block_offset;
-
-            blocks.push(Block::new(id, start, end));
+        // A resumed run only trusts the manifest if the server still agrees
+        // on the length and still supports ranged requests; each block is
+        // only resumed if its exact byte range also still matches, so a
+        // partitioning change between runs can't apply stale progress.
+        match Manifest::load_matching(&save_as, uri, len) {
+            Some(manifest) if flag_range => {
+                for b in blocks.iter_mut() {
+                    if manifest.is_done_for(b.id, b.start, b.end) {
+                        b.is_done = true;
+                    }
+                }
+            }
+            Some(_) => {
+                let _ = Manifest::remove(&save_as);
+            }
+            None => {}
         }
 
         Self {
-            block_offset,
-            block_offset_head,
             blocks,
-            blocks_count,
-            has_write: 0,
             len,
-            save_as: format!("{save_as}.{file_type}"),
-            suffix: file_type.to_string(),
-            thread,
+            save_as,
+            partition,
             uri: uri.to_string(),
-            flag_range: match headers.get("accept-ranges") {
-                None => false,
-                Some(v) => v.to_str().unwrap().eq("bytes"),
-            },
+            flag_range,
+            checksum,
+            file_type,
+            segments: SegmentIndex::build(len, segment_policy),
         }
     }
-}
 
-//Content-Length
-//Content-Type
-//Content-MD5
+    /// Path the blocks are actually written to while the download is in
+    /// progress; renamed to `save_as` once every block reports done.
+    fn working_path(&self) -> String {
+        format!("{}.part", self.save_as)
+    }
+}
 
 #[derive(Clone, Copy, Debug)]
 struct Block {
@@ -199,52 +239,104 @@ impl Block {
         &self,
         client: &Client<hyper_tls::HttpsConnector<HttpConnector>, Body>,
         info: &FileInfo,
-    ) -> Result<(), ()> {
-        let request = Request::builder()
-            .method(Method::GET)
-            .header("range", format!("bytes={}-{}", self.start, self.end))
-            .uri(info.uri.as_str())
-            .body(Body::empty())
-            .unwrap();
-        let response = client.request(request).await.unwrap();
-        let bytes = hyper::body::to_bytes(response).await.unwrap();
+        manifest: &SharedManifest,
+    ) -> Result<(), DownloadError> {
+        if self.is_done {
+            return Ok(());
+        }
 
-        write_file(info.save_as.as_str(), &bytes, self.start)
-            .await
-            .unwrap();
+        let working_path = info.working_path();
+        let writer = SegmentWriter::new(&working_path, &info.segments);
+
+        fetch_range_streaming_retryable(client, info.uri.as_str(), self.start, self.end, &writer)
+            .await?;
+
+        let mut manifest = manifest.lock().unwrap();
+        manifest.mark_done(self.id);
+        manifest.save(&info.save_as)?;
 
         Ok(())
     }
 }
 
-// #[cfg(any(linux))]
-// async fn write_file(filepath: &str, bytes: &[u8], offset: u64) -> Result<usize, std::io::Error> {
-//     tokio_uring::start(async {
-//         let file = OpenOptions::new()
-//             .create(true)
-//             .write(true)
-//             .open("filepath")
-//             .await?;
-//         let (res, _) = file.write_at(bytes. offset).await;
-//         let n = res?;
-//         file.close().await?;
-//     })
-// }
-
-#[cfg(any(unix))]
-async fn write_file(filepath: &str, bytes: &[u8], offset: u64) -> Result<usize, std::io::Error> {
-    let file = fs::OpenOptions::new()
-        .create(true)
-        .write(true)
-        .open(filepath)?;
-    file.write_at(&bytes, offset)
+/// Issues a single `GET bytes={start}-{end}` request and streams the
+/// response straight through `writer` at the matching offset, so peak memory
+/// is bounded by one frame rather than the whole block. Validates that the
+/// response is `206 Partial Content` and that the total bytes written match
+/// the requested range.
+async fn fetch_range_streaming(
+    client: &Client<hyper_tls::HttpsConnector<HttpConnector>, Body>,
+    uri: &str,
+    start: u64,
+    end: u64,
+    writer: &SegmentWriter<'_>,
+) -> Result<(), DownloadError> {
+    let request = Request::builder()
+        .method(Method::GET)
+        .header("range", format!("bytes={start}-{end}"))
+        .uri(uri)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = client.request(request).await.map_err(DownloadError::Request)?;
+
+    if response.status() != hyper::StatusCode::PARTIAL_CONTENT {
+        return Err(DownloadError::UnexpectedStatus(response.status()));
+    }
+
+    let expected = end - start + 1;
+    let mut body = response.into_body();
+    let mut written = 0u64;
+
+    while let Some(frame) = body.data().await {
+        let frame = frame.map_err(DownloadError::Request)?;
+        writer.write_at(&frame, start + written)?;
+        written += frame.len() as u64;
+    }
+
+    if written != expected {
+        return Err(DownloadError::ShortRead {
+            expected,
+            got: written,
+        });
+    }
+
+    Ok(())
 }
 
-mod test {
-    #[test]
-    async fn write_bytes_to_file_() {
-        write_bytes_to_file("w.txt", "aaa".as_bytes(), 1)
-            .await
-            .unwrap();
+/// Wraps [`fetch_range_streaming`] with exponential backoff so a transient
+/// failure (dropped connection, short read) doesn't take the whole block
+/// down. On a short read we already wrote `got` bytes at the front of the
+/// range, so the next attempt resumes from `start + got` instead of redoing
+/// the whole block; any other failure re-requests from the same point as
+/// the failed attempt.
+async fn fetch_range_streaming_retryable(
+    client: &Client<hyper_tls::HttpsConnector<HttpConnector>, Body>,
+    uri: &str,
+    start: u64,
+    end: u64,
+    writer: &SegmentWriter<'_>,
+) -> Result<(), DownloadError> {
+    let mut attempt = 0;
+    let mut resume_from = start;
+
+    loop {
+        match fetch_range_streaming(client, uri, resume_from, end, writer).await {
+            Ok(()) => return Ok(()),
+            Err(err) if attempt >= MAX_RETRIES => {
+                return Err(DownloadError::RetriesExhausted(Box::new(err)))
+            }
+            Err(err) => {
+                if let DownloadError::ShortRead { got, .. } = err {
+                    resume_from += got;
+                }
+
+                attempt += 1;
+                log::warn!(
+                    "range request failed ({err}), retrying {resume_from}-{end} ({attempt}/{MAX_RETRIES})"
+                );
+                tokio::time::sleep(RETRY_BASE_BACKOFF * 2u32.pow(attempt - 1)).await;
+            }
+        }
     }
 }
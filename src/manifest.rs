@@ -0,0 +1,99 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Block;
+
+/// Shared handle so every block task can flip its own entry to `is_done`
+/// and flush the manifest without re-reading it from disk first.
+pub type SharedManifest = Arc<Mutex<Manifest>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockState {
+    pub id: u64,
+    pub start: u64,
+    pub end: u64,
+    pub is_done: bool,
+}
+
+impl From<&Block> for BlockState {
+    fn from(b: &Block) -> Self {
+        Self {
+            id: b.id,
+            start: b.start,
+            end: b.end,
+            is_done: b.is_done,
+        }
+    }
+}
+
+/// On-disk checkpoint for a single download, sitting next to the
+/// in-progress file as `<save_as>.part.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub uri: String,
+    pub len: u64,
+    pub blocks: Vec<BlockState>,
+}
+
+impl Manifest {
+    pub fn path_for(save_as: &str) -> PathBuf {
+        PathBuf::from(format!("{save_as}.part.json"))
+    }
+
+    pub fn from_blocks(uri: &str, len: u64, blocks: &[Block]) -> Self {
+        Self {
+            uri: uri.to_string(),
+            len,
+            blocks: blocks.iter().map(BlockState::from).collect(),
+        }
+    }
+
+    /// Loads the manifest next to `save_as`, but only if it still matches
+    /// the `uri`/`len` we're about to download; a stale or foreign manifest
+    /// is treated as absent so the caller falls back to a fresh download.
+    pub fn load_matching(save_as: &str, uri: &str, len: u64) -> Option<Self> {
+        let data = fs::read_to_string(Self::path_for(save_as)).ok()?;
+        let manifest: Self = serde_json::from_str(&data).ok()?;
+
+        if manifest.uri == uri && manifest.len == len {
+            Some(manifest)
+        } else {
+            None
+        }
+    }
+
+    /// Whether block `id` is marked done *and* still covers the exact same
+    /// `[start, end]` range — guards against a resume applying stale
+    /// progress after the partitioning scheme (thread count, chunk size)
+    /// changed between runs.
+    pub fn is_done_for(&self, id: u64, start: u64, end: u64) -> bool {
+        self.blocks
+            .iter()
+            .any(|b| b.id == id && b.start == start && b.end == end && b.is_done)
+    }
+
+    pub fn mark_done(&mut self, id: u64) {
+        if let Some(b) = self.blocks.iter_mut().find(|b| b.id == id) {
+            b.is_done = true;
+        }
+    }
+
+    pub fn save(&self, save_as: &str) -> io::Result<()> {
+        let data = serde_json::to_vec_pretty(self)?;
+
+        fs::write(Self::path_for(save_as), data)
+    }
+
+    pub fn remove(save_as: &str) -> io::Result<()> {
+        match fs::remove_file(Self::path_for(save_as)) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
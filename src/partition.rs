@@ -0,0 +1,50 @@
+/// How a file's byte range is split into blocks before downloading.
+#[derive(Debug, Clone, Copy)]
+pub enum PartitionPolicy {
+    /// Split evenly across a fixed number of blocks.
+    Threads(u8),
+    /// Split into blocks of roughly `bytes` each — more predictable than a
+    /// thread count when downloading files whose size varies widely.
+    #[allow(dead_code)]
+    ChunkSize(u64),
+}
+
+/// Partitions `[0, len)` into contiguous, gap-free `[start, end]` byte
+/// ranges (inclusive, matching HTTP range semantics). Every block is
+/// exactly `len / block_count` bytes except the last, which absorbs the
+/// remainder — so the union of all ranges covers `[0, len)` exactly, with
+/// no range ever reaching past `len - 1`.
+pub fn partition(len: u64, policy: PartitionPolicy) -> Vec<(u64, u64)> {
+    if len == 0 {
+        return Vec::new();
+    }
+
+    let block_count = match policy {
+        PartitionPolicy::Threads(threads) => threads.max(1) as u64,
+        PartitionPolicy::ChunkSize(bytes) => {
+            let bytes = bytes.max(1);
+            len.div_ceil(bytes)
+        }
+    }
+    .min(len);
+
+    let base = len / block_count;
+    let remainder = len % block_count;
+
+    let mut ranges = Vec::with_capacity(block_count as usize);
+    let mut start = 0;
+
+    for i in 0..block_count {
+        let this_len = if i == block_count - 1 {
+            base + remainder
+        } else {
+            base
+        };
+        let end = start + this_len - 1;
+
+        ranges.push((start, end));
+        start += this_len;
+    }
+
+    ranges
+}
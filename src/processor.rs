@@ -0,0 +1,160 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use crate::file_type::FileType;
+
+#[derive(Debug)]
+pub enum ProcessError {
+    Io(std::io::Error),
+    // Only returned by processors behind feature gates this bin is never
+    // built with in the demo; a caller wired up to a non-default feature
+    // set does construct it (see `ffmpeg::FfmpegTranscoder`/
+    // `image_derivatives::ImageDerivatives`).
+    #[allow(dead_code)]
+    Unsupported(FileType),
+    #[cfg(feature = "ffmpeg")]
+    Ffmpeg(String),
+    #[cfg(feature = "image-processing")]
+    Image(image::ImageError),
+    #[cfg(feature = "image-processing")]
+    Blurhash(blurhash::Error),
+}
+
+impl fmt::Display for ProcessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "io error: {e}"),
+            Self::Unsupported(ft) => write!(f, "no processor for {ft}"),
+            #[cfg(feature = "ffmpeg")]
+            Self::Ffmpeg(msg) => write!(f, "ffmpeg failed: {msg}"),
+            #[cfg(feature = "image-processing")]
+            Self::Image(e) => write!(f, "image processing failed: {e}"),
+            #[cfg(feature = "image-processing")]
+            Self::Blurhash(e) => write!(f, "blurhash encoding failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ProcessError {}
+
+impl From<std::io::Error> for ProcessError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// A post-download step dispatched on the detected `FileType`; kept behind
+/// feature flags so the core downloader stays dependency-light when no
+/// transcoding or thumbnailing is needed.
+#[async_trait::async_trait]
+pub trait Processor {
+    async fn process(&self, path: &Path, ft: FileType) -> Result<Vec<PathBuf>, ProcessError>;
+}
+
+/// Picks the processor (if any) registered for `ft`. Returns `None` when
+/// the matching feature isn't compiled in or no processor handles `ft`.
+pub fn processor_for(ft: FileType) -> Option<Box<dyn Processor + Send + Sync>> {
+    match ft {
+        #[cfg(feature = "ffmpeg")]
+        FileType::Mp4 => Some(Box::new(ffmpeg::FfmpegTranscoder)),
+        #[cfg(feature = "image-processing")]
+        FileType::Jpeg | FileType::Png => Some(Box::new(image_derivatives::ImageDerivatives::default())),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "ffmpeg")]
+pub mod ffmpeg {
+    use std::path::{Path, PathBuf};
+
+    use super::{ProcessError, Processor};
+    use crate::file_type::FileType;
+
+    /// Remuxes `Mp4` through ffmpeg with stream copy, mirroring how
+    /// pict-rs shells out to ffmpeg rather than linking against libav
+    /// directly.
+    #[derive(Default)]
+    pub struct FfmpegTranscoder;
+
+    #[async_trait::async_trait]
+    impl Processor for FfmpegTranscoder {
+        async fn process(&self, path: &Path, ft: FileType) -> Result<Vec<PathBuf>, ProcessError> {
+            if !matches!(ft, FileType::Mp4) {
+                return Err(ProcessError::Unsupported(ft));
+            }
+
+            let out = path.with_extension("remux.mp4");
+            let status = tokio::process::Command::new("ffmpeg")
+                .arg("-y")
+                .arg("-i")
+                .arg(path)
+                .args(["-c", "copy"])
+                .arg(&out)
+                .status()
+                .await?;
+
+            if !status.success() {
+                return Err(ProcessError::Ffmpeg(format!(
+                    "ffmpeg exited with {status}"
+                )));
+            }
+
+            Ok(vec![out])
+        }
+    }
+}
+
+#[cfg(feature = "image-processing")]
+pub mod image_derivatives {
+    use std::path::{Path, PathBuf};
+
+    use super::{ProcessError, Processor};
+    use crate::file_type::FileType;
+
+    /// Re-encodes `Jpeg`/`Png` into a WebP derivative plus a BlurHash
+    /// placeholder string, downscaling first so the DCT grid stays cheap
+    /// regardless of the source image's resolution.
+    pub struct ImageDerivatives {
+        pub x_components: u32,
+        pub y_components: u32,
+    }
+
+    impl Default for ImageDerivatives {
+        fn default() -> Self {
+            Self {
+                x_components: 4,
+                y_components: 3,
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Processor for ImageDerivatives {
+        async fn process(&self, path: &Path, ft: FileType) -> Result<Vec<PathBuf>, ProcessError> {
+            if !matches!(ft, FileType::Jpeg | FileType::Png) {
+                return Err(ProcessError::Unsupported(ft));
+            }
+
+            let img = image::open(path).map_err(ProcessError::Image)?;
+
+            let webp_path = path.with_extension("webp");
+            img.save(&webp_path).map_err(ProcessError::Image)?;
+
+            let thumb = img.thumbnail(64, 64).to_rgba8();
+            let hash = blurhash::encode(
+                self.x_components,
+                self.y_components,
+                thumb.width(),
+                thumb.height(),
+                thumb.as_raw(),
+            )
+            .map_err(ProcessError::Blurhash)?;
+            let hash_path = path.with_extension("blurhash.txt");
+            std::fs::write(&hash_path, hash)?;
+
+            Ok(vec![webp_path, hash_path])
+        }
+    }
+}
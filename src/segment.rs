@@ -0,0 +1,188 @@
+use std::{fs, io, os::unix::fs::FileExt};
+
+use serde::{Deserialize, Serialize};
+
+/// How (or whether) the final artifact is split into separate files.
+/// Borrowed from biliup's `Segmentable`, but driven off byte ranges rather
+/// than stream cut points.
+#[derive(Debug, Clone, Copy)]
+pub enum SegmentPolicy {
+    // Not constructed by this bin's demo main (it always passes
+    // `SegmentPolicy::None`), but part of the caller-facing policy surface.
+    #[allow(dead_code)]
+    BySize(u64),
+    #[allow(dead_code)]
+    ByCount(u64),
+    None,
+}
+
+/// One piece of a segmented download. `suffix` is `None` for
+/// [`SegmentPolicy::None`], where the whole file is a single "segment".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Segment {
+    pub suffix: Option<String>,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Segment {
+    pub fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// The path this segment lives at for a given base name (the working
+    /// `*.part` name while downloading, the final `save_as` once renamed).
+    pub fn filename(&self, base: &str) -> String {
+        match &self.suffix {
+            Some(suffix) => format!("{base}.{suffix}"),
+            None => base.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentIndex {
+    pub segments: Vec<Segment>,
+}
+
+impl SegmentIndex {
+    /// Partitions `[0, len)` into contiguous segments under `policy`.
+    pub fn build(len: u64, policy: SegmentPolicy) -> Self {
+        let segment_len = match policy {
+            SegmentPolicy::None => len.max(1),
+            SegmentPolicy::BySize(size) => size.max(1),
+            SegmentPolicy::ByCount(count) => {
+                let count = count.max(1);
+                len.div_ceil(count)
+            }
+        };
+
+        let mut segments = Vec::new();
+        let mut start = 0;
+        let mut number = 1;
+
+        while start < len {
+            let end = (start + segment_len - 1).min(len - 1);
+            segments.push(Segment {
+                suffix: match policy {
+                    SegmentPolicy::None => None,
+                    _ => Some(format!("part{number:04}")),
+                },
+                start,
+                end,
+            });
+            start = end + 1;
+            number += 1;
+        }
+
+        if segments.is_empty() {
+            segments.push(Segment {
+                suffix: None,
+                start: 0,
+                end: 0,
+            });
+        }
+
+        Self { segments }
+    }
+
+    /// Segments overlapping `[start, end]`, as `(segment, local_start,
+    /// local_end)` offsets within that segment — a write spanning a
+    /// boundary naturally comes back as two (or more) entries.
+    fn spans(&self, start: u64, end: u64) -> Vec<(&Segment, u64, u64)> {
+        self.segments
+            .iter()
+            .filter_map(|seg| {
+                let lo = start.max(seg.start);
+                let hi = end.min(seg.end);
+
+                (lo <= hi).then(|| (seg, lo - seg.start, hi - seg.start))
+            })
+            .collect()
+    }
+
+    /// `Some(path)` when this index describes a single, unsegmented file
+    /// (i.e. `SegmentPolicy::None` was used) — the only case where a
+    /// whole-file checksum is meaningful; `None` whenever the artifact is
+    /// actually split across segment files, even if that happens to be
+    /// just one (e.g. `BySize`/`ByCount` with a size larger than the file).
+    pub fn single_file_path(&self, base: &str) -> Option<String> {
+        match self.segments.as_slice() {
+            [seg] if seg.suffix.is_none() => Some(seg.filename(base)),
+            _ => None,
+        }
+    }
+
+    /// Renames every working segment file to its final name; the caller
+    /// writes out the index separately with [`Self::save_index`].
+    pub fn finalize(&self, working_base: &str, final_base: &str) -> io::Result<()> {
+        for seg in &self.segments {
+            fs::rename(seg.filename(working_base), seg.filename(final_base))?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes a small index of each segment's final filename, byte range
+    /// and length, so the pieces can be reassembled or consumed
+    /// independently of this crate.
+    pub fn save_index(&self, final_base: &str) -> io::Result<()> {
+        let entries: Vec<_> = self
+            .segments
+            .iter()
+            .map(|seg| {
+                serde_json::json!({
+                    "filename": seg.filename(final_base),
+                    "start": seg.start,
+                    "end": seg.end,
+                    "len": seg.len(),
+                })
+            })
+            .collect();
+
+        fs::write(
+            format!("{final_base}.segments.json"),
+            serde_json::to_vec_pretty(&entries)?,
+        )
+    }
+}
+
+/// Routes a chunk of bytes covering the global range
+/// `[offset, offset + bytes.len())` to whichever segment file(s) it spans,
+/// splitting the slice at segment boundaries when a write straddles one.
+pub struct SegmentWriter<'a> {
+    base: &'a str,
+    index: &'a SegmentIndex,
+}
+
+impl<'a> SegmentWriter<'a> {
+    pub fn new(base: &'a str, index: &'a SegmentIndex) -> Self {
+        Self { base, index }
+    }
+
+    pub fn write_at(&self, bytes: &[u8], offset: u64) -> io::Result<()> {
+        if bytes.is_empty() {
+            return Ok(());
+        }
+
+        let end = offset + bytes.len() as u64 - 1;
+
+        for (seg, local_start, local_end) in self.index.spans(offset, end) {
+            let slice_start = (seg.start + local_start - offset) as usize;
+            let slice_len = (local_end - local_start + 1) as usize;
+            let slice = &bytes[slice_start..slice_start + slice_len];
+
+            // Each segment file is written at arbitrary offsets across many
+            // calls (one per response frame); truncating here would wipe out
+            // bytes an earlier call already placed further into the file.
+            #[allow(clippy::suspicious_open_options)]
+            let file = fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .open(seg.filename(self.base))?;
+            file.write_at(slice, local_start)?;
+        }
+
+        Ok(())
+    }
+}